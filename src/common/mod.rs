@@ -0,0 +1,2 @@
+pub mod padding;
+pub mod safetensors;
@@ -0,0 +1,23 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bert::embeddings::BertEmbedding;
+
+/// Embeddings implementations that have a fixed pad token id, letting
+/// callers (e.g. `SentenceEmbeddingsModel`) derive an attention mask without
+/// requiring every caller to know (and potentially get wrong) their model's
+/// padding convention.
+pub trait PaddedEmbedding: BertEmbedding {
+    /// The pad token id this embeddings implementation was trained with
+    /// (e.g. `1` for Roberta, matching `EmbeddingConfig::padding_idx` and
+    /// `create_position_ids_from_input_ids`).
+    fn padding_index() -> i64;
+}
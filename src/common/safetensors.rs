@@ -0,0 +1,207 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use tch::{nn, Kind, Tensor};
+
+use crate::common::error::RustBertError;
+
+/// Tensor metadata as stored in the `.safetensors` header: its dtype, shape
+/// and the half-open byte range of its data within the blob that follows
+/// the header.
+struct TensorInfo {
+    dtype: String,
+    shape: Vec<i64>,
+    data_offsets: (usize, usize),
+}
+
+fn dtype_to_kind(dtype: &str) -> Result<Kind, RustBertError> {
+    Ok(match dtype {
+        "F64" => Kind::Double,
+        "F32" => Kind::Float,
+        "F16" => Kind::Half,
+        "BF16" => Kind::BFloat16,
+        "I64" => Kind::Int64,
+        "I32" => Kind::Int,
+        "I16" => Kind::Int16,
+        "I8" => Kind::Int8,
+        "U8" => Kind::Uint8,
+        "BOOL" => Kind::Bool,
+        _ => {
+            return Err(RustBertError::UnsupportedError(format!(
+                "Unsupported safetensors dtype: {}",
+                dtype
+            )));
+        }
+    })
+}
+
+/// Reads the tensors stored in a `.safetensors` file and returns them as a
+/// name -> `Tensor` map, ready to be copied into a `VarStore`.
+fn read_safetensors<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Tensor>, RustBertError> {
+    let mut file = File::open(path.as_ref())
+        .map_err(|e| RustBertError::IOError(e.to_string()))?;
+
+    let mut header_len_bytes = [0u8; 8];
+    file.read_exact(&mut header_len_bytes)
+        .map_err(|e| RustBertError::IOError(e.to_string()))?;
+    let header_len = u64::from_le_bytes(header_len_bytes) as usize;
+
+    let mut header_bytes = vec![0u8; header_len];
+    file.read_exact(&mut header_bytes)
+        .map_err(|e| RustBertError::IOError(e.to_string()))?;
+    let header: HashMap<String, serde_json::Value> = serde_json::from_slice(&header_bytes)
+        .map_err(|e| RustBertError::IOError(e.to_string()))?;
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)
+        .map_err(|e| RustBertError::IOError(e.to_string()))?;
+
+    let mut tensors = HashMap::new();
+    for (name, value) in header.iter() {
+        // The `__metadata__` entry is not a tensor and carries free-form information.
+        if name == "__metadata__" {
+            continue;
+        }
+        let info: TensorInfo = TensorInfo {
+            dtype: value["dtype"].as_str().unwrap_or_default().to_string(),
+            shape: value["shape"]
+                .as_array()
+                .unwrap_or(&Vec::new())
+                .iter()
+                .map(|v| v.as_i64().unwrap_or_default())
+                .collect(),
+            data_offsets: (
+                value["data_offsets"][0].as_u64().unwrap_or_default() as usize,
+                value["data_offsets"][1].as_u64().unwrap_or_default() as usize,
+            ),
+        };
+        let kind = dtype_to_kind(&info.dtype)?;
+        let (start, end) = info.data_offsets;
+        let bytes = &data[start..end];
+        let tensor = Tensor::of_data_size(bytes, &info.shape, kind);
+        tensors.insert(name.clone(), tensor);
+    }
+    Ok(tensors)
+}
+
+/// Extension trait adding `.safetensors` support to [`tch::nn::VarStore`],
+/// complementing the `.ot` loading already provided by `tch`.
+pub trait SafetensorsLoad {
+    /// Loads the weights stored in a `.safetensors` file into the variables
+    /// already declared on this `VarStore`, matched by name (e.g.
+    /// `word_embeddings`, `position_embeddings`, `LayerNorm`).
+    fn load_safetensors<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RustBertError>;
+}
+
+impl SafetensorsLoad for nn::VarStore {
+    fn load_safetensors<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RustBertError> {
+        let mut tensors = read_safetensors(path)?;
+        let mut variables = self.variables();
+
+        let declared: HashSet<&String> = variables.keys().collect();
+        let found: HashSet<&String> = tensors.keys().collect();
+
+        let mut missing: Vec<&str> = declared
+            .difference(&found)
+            .map(|name| name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Safetensors file is missing variables declared on the VarStore: {}",
+                missing.join(", ")
+            )));
+        }
+
+        let mut unmatched: Vec<&str> = found
+            .difference(&declared)
+            .map(|name| name.as_str())
+            .collect();
+        if !unmatched.is_empty() {
+            unmatched.sort_unstable();
+            return Err(RustBertError::InvalidConfigurationError(format!(
+                "Safetensors file contains tensors not declared on the VarStore: {}",
+                unmatched.join(", ")
+            )));
+        }
+
+        tch::no_grad(|| {
+            for (name, target) in variables.iter_mut() {
+                let source = tensors.remove(name).expect("presence checked above");
+                target.copy_(&source);
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal single-tensor `.safetensors` blob: an 8-byte
+    /// little-endian header length, the JSON header, then the raw tensor bytes.
+    fn sample_safetensors_bytes() -> Vec<u8> {
+        let values: [f32; 4] = [1.0, 2.0, 3.0, 4.0];
+        let mut data = Vec::new();
+        for value in values.iter() {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let header = format!(
+            "{{\"weight\":{{\"dtype\":\"F32\",\"shape\":[2,2],\"data_offsets\":[0,{}]}}}}",
+            data.len()
+        );
+        let header_bytes = header.into_bytes();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(header_bytes.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&header_bytes);
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    #[test]
+    fn read_safetensors_reinterprets_raw_bytes_as_the_declared_dtype_and_shape() {
+        let path = std::env::temp_dir().join("rust_bert_test_sample.safetensors");
+        std::fs::write(&path, sample_safetensors_bytes()).unwrap();
+
+        let tensors = read_safetensors(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let tensor = tensors.get("weight").unwrap();
+        assert_eq!(tensor.size(), vec![2, 2]);
+        assert_eq!(tensor.kind(), Kind::Float);
+        assert_eq!(Vec::<f32>::from(tensor), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn load_safetensors_errors_instead_of_silently_leaving_a_declared_variable_uninitialized() {
+        let path = std::env::temp_dir().join("rust_bert_test_missing.safetensors");
+        std::fs::write(&path, sample_safetensors_bytes()).unwrap();
+
+        let mut var_store = nn::VarStore::new(tch::Device::Cpu);
+        let _ = var_store.root().var("weight", &[2, 2], nn::Init::Const(0.0));
+        // Declared on the VarStore but absent from the file - e.g. a naming
+        // mismatch between the checkpoint and this crate's variable paths.
+        let _ = var_store.root().var("bias", &[2], nn::Init::Const(0.0));
+
+        let result = var_store.load_safetensors(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,2 @@
+//! # Ready-to-use pipelines built on top of the transformer models
+pub mod sentence_embeddings;
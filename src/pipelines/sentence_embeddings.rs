@@ -0,0 +1,294 @@
+// Copyright 2020 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tch::{nn, Device, Kind, Tensor};
+use crate::bert::{BertModel, BertModelOutput};
+use crate::common::error::RustBertError;
+use crate::common::padding::PaddedEmbedding;
+use crate::common::safetensors::SafetensorsLoad;
+use crate::resources::hub::{HubResource, HubWeightFormat, ResolvedHubModel};
+use crate::BertConfig;
+
+/// Strategy used to reduce a sequence of token embeddings down to a single
+/// fixed-size sentence vector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoolingStrategy {
+    /// Average the token embeddings, weighted by the attention mask.
+    Mean,
+    /// Use the embedding of the first (`[CLS]`/`<s>`) token.
+    Cls,
+    /// Take the element-wise maximum over the non-padding token embeddings.
+    MaxToken,
+}
+
+impl Default for PoolingStrategy {
+    fn default() -> PoolingStrategy {
+        PoolingStrategy::Mean
+    }
+}
+
+pub struct SentenceEmbeddingsConfig {
+    pub pooling_strategy: PoolingStrategy,
+    pub normalize_embeddings: bool,
+    pub score_normalization: Option<DistributionShift>,
+    pub padding_strategy: PaddingStrategy,
+}
+
+impl Default for SentenceEmbeddingsConfig {
+    fn default() -> SentenceEmbeddingsConfig {
+        SentenceEmbeddingsConfig {
+            pooling_strategy: PoolingStrategy::Mean,
+            normalize_embeddings: true,
+            score_normalization: None,
+            padding_strategy: PaddingStrategy::LongestInBatch,
+        }
+    }
+}
+
+/// Controls how a batch of ragged token-id sequences is right-padded before
+/// being handed to the transformer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaddingStrategy {
+    /// Pad every sequence in the batch up to the length of its longest member.
+    LongestInBatch,
+    /// Pad (and truncate if necessary) every sequence to a fixed length.
+    FixedLength(i64),
+}
+
+/// Rescales a raw similarity score onto a `[0, 1]` range via a shifted sigmoid,
+/// so thresholds stay comparable across checkpoints that otherwise produce
+/// scores on very different numeric scales.
+///
+/// `mean` and `std` are typically fit on a representative set of (query,
+/// passage) pairs for a given model; see the model card for suggested
+/// constants.
+#[derive(Debug, Clone, Copy)]
+pub struct DistributionShift {
+    pub mean: f32,
+    pub std: f32,
+}
+
+impl DistributionShift {
+    /// Maps a raw similarity score to `[0, 1]`.
+    pub fn normalize(&self, score: f32) -> f32 {
+        let shifted = 1f32 / (1f32 + (-(score - self.mean) / self.std).exp());
+        shifted.clamp(0f32, 1f32)
+    }
+}
+
+/// Turns batches of tokenized text into fixed-size sentence embeddings,
+/// suitable for semantic search or clustering.
+pub struct SentenceEmbeddingsModel<T: PaddedEmbedding> {
+    transformer: BertModel<T>,
+    pooling_strategy: PoolingStrategy,
+    normalize_embeddings: bool,
+    score_normalization: Option<DistributionShift>,
+    padding_strategy: PaddingStrategy,
+    padding_index: i64,
+}
+
+impl<T: PaddedEmbedding> SentenceEmbeddingsModel<T> {
+    pub fn new(p: &nn::Path, config: &BertConfig, embeddings_config: SentenceEmbeddingsConfig) -> SentenceEmbeddingsModel<T> {
+        let transformer = BertModel::new(p, config);
+        SentenceEmbeddingsModel {
+            transformer,
+            pooling_strategy: embeddings_config.pooling_strategy,
+            normalize_embeddings: embeddings_config.normalize_embeddings,
+            score_normalization: embeddings_config.score_normalization,
+            padding_strategy: embeddings_config.padding_strategy,
+            padding_index: T::padding_index(),
+        }
+    }
+
+    /// Resolves `hub_resource` (downloading and caching its config, tokenizer
+    /// files and weights if needed), builds the model and loads the weights
+    /// into it in the format selected by `hub_resource.weight_format` (`.ot`
+    /// or `.safetensors`) - the end-to-end "fetch from hub, then construct"
+    /// path these pieces exist for.
+    ///
+    /// Returns the model, the `VarStore` backing its weights (which, as with
+    /// `BertModel::new(&vs.root(), ...)`, must be kept alive for as long as
+    /// the model is used), and the [`ResolvedHubModel`] so callers can load
+    /// a tokenizer from the cached `vocab_path`/`merges_path`.
+    pub fn from_hub(hub_resource: &HubResource, device: Device, embeddings_config: SentenceEmbeddingsConfig) -> Result<(SentenceEmbeddingsModel<T>, nn::VarStore, ResolvedHubModel), RustBertError> {
+        let resolved = hub_resource.resolve()?;
+        let config = BertConfig::from_file(&resolved.config_path);
+        // Both loaders copy into the variables already declared on the
+        // `VarStore`, so the model must be built before the weights are loaded.
+        let mut var_store = nn::VarStore::new(device);
+        let model = SentenceEmbeddingsModel::new(&var_store.root(), &config, embeddings_config);
+        match resolved.weight_format {
+            HubWeightFormat::PyTorchOt => var_store
+                .load(&resolved.weights_path)
+                .map_err(|e| RustBertError::IOError(e.to_string()))?,
+            HubWeightFormat::SafeTensors => var_store.load_safetensors(&resolved.weights_path)?,
+        }
+        Ok((model, var_store, resolved))
+    }
+
+    /// Pads a batch of ragged token-id sequences according to the configured
+    /// [`PaddingStrategy`] and returns the padded `input_ids` alongside the
+    /// attention mask derived from it (`input_ids.ne(padding_index)`), so
+    /// callers no longer need to re-implement this bookkeeping themselves.
+    pub fn pad_batch(&self, input_ids: &[Vec<i64>]) -> (Tensor, Tensor) {
+        let target_length = match self.padding_strategy {
+            PaddingStrategy::LongestInBatch => {
+                input_ids.iter().map(Vec::len).max().unwrap_or(0) as i64
+            }
+            PaddingStrategy::FixedLength(max_length) => max_length,
+        };
+
+        let padded: Vec<Vec<i64>> = input_ids
+            .iter()
+            .map(|sequence| {
+                let mut sequence = sequence.clone();
+                sequence.truncate(target_length as usize);
+                sequence.resize(target_length as usize, self.padding_index);
+                sequence
+            })
+            .collect();
+
+        let batch_size = padded.len() as i64;
+        let flattened: Vec<i64> = padded.into_iter().flatten().collect();
+        let input_ids = Tensor::of_slice(&flattened).view((batch_size, target_length));
+        let attention_mask = input_ids.ne(self.padding_index);
+        (input_ids, attention_mask)
+    }
+
+    /// Pads a batch of ragged token-id sequences and encodes it into sentence
+    /// embeddings in a single call.
+    pub fn encode_batch(&self, input_ids: &[Vec<i64>]) -> Result<Tensor, RustBertError> {
+        let (input_ids, attention_mask) = self.pad_batch(input_ids);
+        self.encode(&input_ids, &attention_mask)
+    }
+
+    /// Computes the pairwise similarity (dot product of the, typically L2-normalized,
+    /// sentence embeddings) between `source` and `other`, applying the configured
+    /// [`DistributionShift`] if any to land the score on a `[0, 1]` scale.
+    pub fn similarity(&self, source: &Tensor, other: &Tensor) -> f32 {
+        let score = f32::from(source.dot(other));
+        match &self.score_normalization {
+            Some(distribution_shift) => distribution_shift.normalize(score),
+            None => score,
+        }
+    }
+
+    /// Encodes a batch of `input_ids` into sentence embeddings of shape `[batch, hidden]`.
+    ///
+    /// `attention_mask` should have value `1` for real tokens and `0` for padding.
+    pub fn encode(&self, input_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor, RustBertError> {
+        let BertModelOutput { hidden_state, .. } = self.transformer.forward_t(
+            Some(input_ids.shallow_clone()),
+            Some(attention_mask.shallow_clone()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )?;
+
+        let pooled = self.pool(&hidden_state, attention_mask);
+        Ok(if self.normalize_embeddings {
+            Self::l2_normalize(&pooled)
+        } else {
+            pooled
+        })
+    }
+
+    fn pool(&self, hidden_state: &Tensor, attention_mask: &Tensor) -> Tensor {
+        match self.pooling_strategy {
+            PoolingStrategy::Mean => Self::mean_pooling(hidden_state, attention_mask),
+            PoolingStrategy::Cls => hidden_state.select(1, 0),
+            PoolingStrategy::MaxToken => Self::max_pooling(hidden_state, attention_mask),
+        }
+    }
+
+    fn mean_pooling(hidden_state: &Tensor, attention_mask: &Tensor) -> Tensor {
+        let mask = attention_mask
+            .unsqueeze(-1)
+            .expand_as(hidden_state)
+            .to_kind(Kind::Float);
+        let summed = (hidden_state * &mask).sum1(&[1], false, Kind::Float);
+        let counts = mask.sum1(&[1], false, Kind::Float).clamp_min(1e-9);
+        summed / counts
+    }
+
+    fn max_pooling(hidden_state: &Tensor, attention_mask: &Tensor) -> Tensor {
+        let mask = attention_mask
+            .unsqueeze(-1)
+            .expand_as(hidden_state)
+            .to_kind(Kind::Float);
+        let masked = hidden_state * &mask + (1 - &mask) * -1e9;
+        masked.max1(&[1], false).0
+    }
+
+    fn l2_normalize(embeddings: &Tensor) -> Tensor {
+        let norm = embeddings.pow(2.0).sum1(&[-1], true, Kind::Float).sqrt().clamp_min(1e-12);
+        embeddings / norm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roberta::embeddings::RobertaEmbeddings;
+
+    #[test]
+    fn roberta_padding_index_pads_shorter_sequences_with_token_id_1() {
+        let padded: Vec<Vec<i64>> = vec![vec![4], vec![4; 5]]
+            .into_iter()
+            .map(|sequence| {
+                let mut sequence = sequence;
+                sequence.resize(5, RobertaEmbeddings::padding_index());
+                sequence
+            })
+            .collect();
+
+        // Roberta's pad token id is `1`, not `0` - the shorter sequence must
+        // be padded with it, matching `create_position_ids_from_input_ids`.
+        assert_eq!(padded[0], vec![4, 1, 1, 1, 1]);
+        assert_eq!(padded[1], vec![4, 4, 4, 4, 4]);
+    }
+
+    #[test]
+    fn mean_pooling_ignores_padding_tokens() {
+        // A single sequence of 2 tokens followed by 1 padding position; the
+        // padding's (1.0, 1.0) embedding must not affect the average.
+        let hidden_state = Tensor::of_slice(&[1.0f32, 1.0, 3.0, 1.0, 1.0, 1.0])
+            .view((1, 3, 2));
+        let attention_mask = Tensor::of_slice(&[1i64, 1, 0]).view((1, 3));
+
+        let pooled = SentenceEmbeddingsModel::<RobertaEmbeddings>::mean_pooling(&hidden_state, &attention_mask);
+
+        assert_eq!(Vec::<f32>::from(&pooled), vec![2.0, 1.0]);
+    }
+
+    #[test]
+    fn l2_normalize_produces_unit_vectors() {
+        let embeddings = Tensor::of_slice(&[3.0f32, 4.0]).view((1, 2));
+
+        let normalized = SentenceEmbeddingsModel::<RobertaEmbeddings>::l2_normalize(&embeddings);
+
+        let values = Vec::<f32>::from(&normalized);
+        assert!((values[0] - 0.6).abs() < 1e-6);
+        assert!((values[1] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn distribution_shift_centers_the_mean_score_and_clamps_the_range() {
+        let distribution_shift = DistributionShift { mean: 0.5, std: 0.1 };
+
+        assert!((distribution_shift.normalize(0.5) - 0.5).abs() < 1e-6);
+        assert!(distribution_shift.normalize(100.0) <= 1.0);
+        assert!(distribution_shift.normalize(-100.0) >= 0.0);
+    }
+}
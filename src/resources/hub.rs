@@ -0,0 +1,157 @@
+// Copyright 2022 Guillaume Becquin
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//     http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use cached_path::{Cache, Options};
+use tch::{nn, Device};
+
+use crate::common::error::RustBertError;
+use crate::common::safetensors::SafetensorsLoad;
+
+const HUB_ENDPOINT: &str = "https://huggingface.co";
+const DEFAULT_REVISION: &str = "main";
+
+/// Weight format to resolve from a Hugging Face Hub repository.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HubWeightFormat {
+    /// PyTorch weights converted to the `.ot` format expected by `tch`.
+    PyTorchOt,
+    /// Native `.safetensors` weights, loaded via [`crate::common::safetensors`].
+    SafeTensors,
+}
+
+impl HubWeightFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            HubWeightFormat::PyTorchOt => "rust_model.ot",
+            HubWeightFormat::SafeTensors => "model.safetensors",
+        }
+    }
+}
+
+/// Points at a model hosted on the Hugging Face Hub, optionally pinned to an
+/// exact revision (commit hash or tag) for reproducible downloads.
+pub struct HubResource {
+    pub repo_id: String,
+    pub revision: Option<String>,
+    pub weight_format: HubWeightFormat,
+}
+
+impl HubResource {
+    pub fn new(repo_id: impl Into<String>, weight_format: HubWeightFormat) -> HubResource {
+        HubResource {
+            repo_id: repo_id.into(),
+            revision: None,
+            weight_format,
+        }
+    }
+
+    /// Pin this resource to an exact revision, falling back to `main` if unset.
+    pub fn at_revision(mut self, revision: impl Into<String>) -> HubResource {
+        self.revision = Some(revision.into());
+        self
+    }
+
+    fn revision(&self) -> &str {
+        self.revision.as_deref().unwrap_or(DEFAULT_REVISION)
+    }
+
+    fn file_url(&self, file_name: &str) -> String {
+        format!(
+            "{}/{}/resolve/{}/{}",
+            HUB_ENDPOINT,
+            self.repo_id,
+            self.revision(),
+            file_name
+        )
+    }
+
+    /// Downloads (or reuses the cached copy of) a single file from the repository,
+    /// returning its local path. When a revision is pinned, the cache's default
+    /// freshness policy applies; otherwise the freshness lifetime is set to
+    /// zero so the cached copy is revalidated against `main` on every call.
+    fn fetch(&self, file_name: &str) -> Result<PathBuf, RustBertError> {
+        self.fetch_inner(file_name)
+            .map_err(|e| RustBertError::IOError(e.to_string()))
+    }
+
+    /// Like [`Self::fetch`], but treats a 404 from the hub as "this file does
+    /// not exist in this repository" rather than a failure, since some repos
+    /// omit optional files (e.g. `merges.txt` for non-BPE tokenizers). Any
+    /// other failure (network error, auth error, corrupt cache entry) is
+    /// still propagated instead of being silently treated as "absent".
+    fn fetch_optional(&self, file_name: &str) -> Result<Option<PathBuf>, RustBertError> {
+        match self.fetch_inner(file_name) {
+            Ok(path) => Ok(Some(path)),
+            Err(cached_path::Error::HttpStatusError(404)) => Ok(None),
+            Err(e) => Err(RustBertError::IOError(e.to_string())),
+        }
+    }
+
+    fn fetch_inner(&self, file_name: &str) -> Result<PathBuf, cached_path::Error> {
+        let url = self.file_url(file_name);
+        let mut options = Options::default();
+        if self.revision.is_some() {
+            options = options.subdir("rust-bert");
+        } else {
+            options = options.subdir("rust-bert").freshness_lifetime(0);
+        }
+        let cache = Cache::builder()
+            .build()
+            .map_err(|e| cached_path::Error::IoError(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+        cache.cached_path_with_options(&url, &options)
+    }
+
+    /// Resolves the config, vocabulary/merges and weight files for this repository,
+    /// downloading and caching them locally.
+    pub fn resolve(&self) -> Result<ResolvedHubModel, RustBertError> {
+        let config_path = self.fetch("config.json")?;
+        let vocab_path = self.fetch("vocab.json")?;
+        let merges_path = self.fetch_optional("merges.txt")?;
+        let weights_path = self.fetch(self.weight_format.file_name())?;
+
+        Ok(ResolvedHubModel {
+            config_path,
+            vocab_path,
+            merges_path,
+            weights_path,
+            weight_format: self.weight_format,
+        })
+    }
+}
+
+/// Local paths for a model resolved from the Hugging Face Hub, ready to be
+/// handed to `BertConfig::from_file` / `RobertaEmbeddings::new` and friends.
+pub struct ResolvedHubModel {
+    pub config_path: PathBuf,
+    pub vocab_path: PathBuf,
+    pub merges_path: Option<PathBuf>,
+    pub weights_path: PathBuf,
+    pub weight_format: HubWeightFormat,
+}
+
+impl ResolvedHubModel {
+    /// Builds a `VarStore` on `device` and loads the resolved weights into it,
+    /// dispatching to the `.ot` or `.safetensors` loader according to
+    /// [`Self::weight_format`] so callers don't need an offline conversion
+    /// step regardless of which format the repository ships.
+    pub fn load_var_store(&self, device: Device) -> Result<nn::VarStore, RustBertError> {
+        let mut var_store = nn::VarStore::new(device);
+        match self.weight_format {
+            HubWeightFormat::PyTorchOt => var_store
+                .load(&self.weights_path)
+                .map_err(|e| RustBertError::IOError(e.to_string()))?,
+            HubWeightFormat::SafeTensors => var_store.load_safetensors(&self.weights_path)?,
+        }
+        Ok(var_store)
+    }
+}
@@ -14,9 +14,14 @@
 use tch::{nn, Tensor, Kind};
 use crate::common::dropout::Dropout;
 use crate::bert::embeddings::BertEmbedding;
+use crate::common::padding::PaddedEmbedding;
 use crate::BertConfig;
 use tch::nn::{EmbeddingConfig, embedding};
 
+/// Roberta's pad token id, shared between the embedding lookup (`new`) and
+/// [`PaddedEmbedding::padding_index`] so the two can't drift apart.
+const PAD_TOKEN_ID: i64 = 1;
+
 #[derive(Debug)]
 pub struct RobertaEmbeddings {
     word_embeddings: nn::Embedding,
@@ -43,7 +48,7 @@ impl RobertaEmbeddings {
 
 impl BertEmbedding for RobertaEmbeddings {
     fn new(p: &nn::Path, config: &BertConfig) -> RobertaEmbeddings {
-        let embedding_config = EmbeddingConfig { padding_idx: 1, ..Default::default() };
+        let embedding_config = EmbeddingConfig { padding_idx: PAD_TOKEN_ID, ..Default::default() };
 
         let word_embeddings: nn::Embedding = embedding(p / "word_embeddings",
                                                        config.vocab_size,
@@ -63,7 +68,7 @@ impl BertEmbedding for RobertaEmbeddings {
         let layer_norm_config = nn::LayerNormConfig { eps: 1e-12, ..Default::default() };
         let layer_norm: nn::LayerNorm = nn::layer_norm(p / "LayerNorm", vec![config.hidden_size], layer_norm_config);
         let dropout: Dropout = Dropout::new(config.hidden_dropout_prob);
-        RobertaEmbeddings { word_embeddings, position_embeddings, token_type_embeddings, layer_norm, dropout, padding_index: 1 }
+        RobertaEmbeddings { word_embeddings, position_embeddings, token_type_embeddings, layer_norm, dropout, padding_index: PAD_TOKEN_ID }
     }
 
     fn forward_t(&self,
@@ -103,4 +108,20 @@ impl BertEmbedding for RobertaEmbeddings {
         let input_embeddings: Tensor = input_embeddings + position_embeddings + token_type_embeddings;
         Ok(input_embeddings.apply(&self.layer_norm).apply_t(&self.dropout, train))
     }
+}
+
+impl PaddedEmbedding for RobertaEmbeddings {
+    fn padding_index() -> i64 {
+        PAD_TOKEN_ID
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padding_index_matches_the_embedding_config_padding_idx() {
+        assert_eq!(RobertaEmbeddings::padding_index(), PAD_TOKEN_ID);
+    }
 }
\ No newline at end of file